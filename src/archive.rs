@@ -0,0 +1,53 @@
+use goblin::archive::Archive;
+
+use crate::{MRange, MetaData, Tag};
+
+// "!<arch>\n"
+const AR_MAGIC_LEN: u64 = 8;
+const AR_HEADER_LEN: u64 = 60;
+
+/// Populate `franges` with one `Meta` entry per archive member header, plus
+/// the symbol index, if the archive has one.
+pub(crate) fn analyze(archive: &Archive, bytes: &[u8], franges: &mut Vec<(MRange, MetaData)>) {
+    let base = bytes.as_ptr() as usize;
+
+    let mut members = Vec::new();
+    for name in archive.members() {
+        if let Ok(data) = archive.extract(name, bytes) {
+            let content_offset = (data.as_ptr() as usize - base) as u64;
+            let header_offset = content_offset.saturating_sub(AR_HEADER_LEN);
+            members.push((name, header_offset));
+        }
+    }
+
+    // `archive.members()` only lists real members, so a symbol-table
+    // member (written by `ranlib`/the linker) never appears in it. Its
+    // presence shows up instead as a gap between the archive magic and the
+    // first real member's header; without a symbol table, the first real
+    // member starts directly there. An archive without a ranlib'd symbol
+    // table (or a thin archive) has no gap, so no entry should be
+    // synthesized for it.
+    let has_symbol_index = members
+        .first()
+        .map(|&(_, offset)| offset > AR_MAGIC_LEN)
+        .unwrap_or(false);
+    if has_symbol_index {
+        let meta = MetaData {
+            tag: Tag::Meta,
+            name: Some("symbol index".to_string()),
+            memory: None,
+            segment_id: None,
+        };
+        franges.push(((AR_MAGIC_LEN, AR_MAGIC_LEN + AR_HEADER_LEN).into(), meta));
+    }
+
+    for (name, header_offset) in members {
+        let meta = MetaData {
+            tag: Tag::Meta,
+            name: Some(name.to_string()),
+            memory: None,
+            segment_id: None,
+        };
+        franges.push(((header_offset, header_offset + AR_HEADER_LEN).into(), meta));
+    }
+}
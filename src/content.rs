@@ -0,0 +1,133 @@
+use crate::{MRange, MetaData, Tag};
+
+const MIN_ASCII_RUN: usize = 4;
+const MIN_ZERO_RUN: usize = 16;
+const ENTROPY_WINDOW: usize = 256;
+const HIGH_ENTROPY_THRESHOLD: f64 = 7.2;
+
+fn is_ascii_printable(b: u8) -> bool {
+    (0x20..=0x7e).contains(&b) || b == b'\t' || b == b'\n'
+}
+
+/// Split the bytes of a single file range, `chunk`, starting at file offset
+/// `base`, into ordered `ASCII`, `Zero`, and `HighEntropy` subranges,
+/// falling back to `parent`'s own tag for everything else.
+pub(crate) fn split(chunk: &[u8], base: u64, parent: &MetaData) -> Vec<(MRange, MetaData)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    let mut remainder_start = 0;
+
+    while i < chunk.len() {
+        let (matched, printable) = ascii_run(&chunk[i..]);
+        if let Some(len) = matched {
+            flush_remainder(chunk, remainder_start, i, base, parent, &mut out);
+            out.push(tagged(base, i, i + len, Tag::ASCII, parent));
+            i += len;
+            remainder_start = i;
+        } else if let Some(len) = zero_run(&chunk[i..]) {
+            flush_remainder(chunk, remainder_start, i, base, parent, &mut out);
+            out.push(tagged(base, i, i + len, Tag::Zero, parent));
+            i += len;
+            remainder_start = i;
+        } else {
+            // `printable` bytes at `i` are already known not to be part of
+            // any ASCII run (same printable prefix, same terminator check),
+            // so skip past all of them instead of re-scanning from i + 1.
+            i += printable.max(1);
+        }
+    }
+    flush_remainder(chunk, remainder_start, chunk.len(), base, parent, &mut out);
+
+    if out.is_empty() {
+        out.push(tagged(base, 0, chunk.len(), parent.tag.clone(), parent));
+    }
+    out
+}
+
+/// Length of a printable-ASCII run of at least `MIN_ASCII_RUN` bytes
+/// terminated by a NUL, starting at the beginning of `bytes`, if any, paired
+/// with the number of leading printable-ASCII bytes regardless of match.
+///
+/// The caller uses the latter to skip straight past a long printable stretch
+/// that turned out not to be NUL-terminated, rather than re-running this
+/// same `take_while` scan from every byte inside it.
+fn ascii_run(bytes: &[u8]) -> (Option<usize>, usize) {
+    let printable = bytes.iter().take_while(|&&b| is_ascii_printable(b)).count();
+    if printable >= MIN_ASCII_RUN && bytes.get(printable) == Some(&0) {
+        (Some(printable + 1), printable)
+    } else {
+        (None, printable)
+    }
+}
+
+/// Length of a run of at least `MIN_ZERO_RUN` zero bytes starting at the
+/// beginning of `bytes`, if any.
+fn zero_run(bytes: &[u8]) -> Option<usize> {
+    let zeroes = bytes.iter().take_while(|&&b| b == 0).count();
+    if zeroes >= MIN_ZERO_RUN {
+        Some(zeroes)
+    } else {
+        None
+    }
+}
+
+fn tagged(base: u64, start: usize, end: usize, tag: Tag, parent: &MetaData) -> (MRange, MetaData) {
+    (
+        MRange::from((base + start as u64, base + end as u64)),
+        MetaData {
+            tag,
+            name: parent.name.clone(),
+            memory: parent.memory.clone(),
+            segment_id: parent.segment_id,
+        },
+    )
+}
+
+/// Tag `chunk[start..end]` (bytes not already claimed by an ASCII or Zero
+/// run) in `ENTROPY_WINDOW`-sized blocks, falling back to `parent`'s own tag
+/// for low-entropy windows, and merging adjacent windows with the same tag.
+fn flush_remainder(
+    chunk: &[u8],
+    start: usize,
+    end: usize,
+    base: u64,
+    parent: &MetaData,
+    out: &mut Vec<(MRange, MetaData)>,
+) {
+    if start >= end {
+        return;
+    }
+    let mut pos = start;
+    while pos < end {
+        let window_end = (pos + ENTROPY_WINDOW).min(end);
+        let tag = if shannon_entropy(&chunk[pos..window_end]) > HIGH_ENTROPY_THRESHOLD {
+            Tag::HighEntropy
+        } else {
+            parent.tag.clone()
+        };
+        match out.last_mut() {
+            Some((range, meta)) if meta.tag == tag && range.max == base + pos as u64 => {
+                range.max = base + window_end as u64;
+            }
+            _ => out.push(tagged(base, pos, window_end, tag, parent)),
+        }
+        pos = window_end;
+    }
+}
+
+/// Shannon entropy, in bits, of the byte-value histogram of `window`.
+fn shannon_entropy(window: &[u8]) -> f64 {
+    let mut histogram = [0u32; 256];
+    for &b in window {
+        histogram[b as usize] += 1;
+    }
+    let len = window.len() as f64;
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
@@ -0,0 +1,109 @@
+use goblin::elf::Elf;
+
+use crate::{MRange, MetaData, Tag};
+
+#[derive(Debug, Clone)]
+/// A single dynamic relocation and what it wires up.
+pub struct Relocation {
+    /// The virtual address the relocation patches.
+    pub offset: u64,
+    /// The symbol the relocation resolves to, if any (e.g. a PLT/GOT entry
+    /// referencing an external function).
+    pub symbol: Option<String>,
+    /// The tag of whichever `memranges` entry the relocated address fell
+    /// inside, as of analysis time.
+    pub target_tag: Option<Tag>,
+}
+
+#[derive(Debug, Clone)]
+/// A runtime linker's-eye view of an ELF: what it imports, what it
+/// exports, and where its functions and objects live.
+pub struct DynamicInfo {
+    pub relocations: Vec<Relocation>,
+    pub needed: Vec<String>,
+    pub soname: Option<String>,
+    pub init: Option<u64>,
+    pub fini: Option<u64>,
+    pub entry: u64,
+    symbols: Vec<(MRange, String)>,
+}
+
+impl DynamicInfo {
+    /// The name of the defined symbol whose span contains `vaddr`, if any.
+    pub fn symbol_at(&self, vaddr: u64) -> Option<&str> {
+        self.symbols
+            .iter()
+            .find(|(range, _)| range.min <= vaddr && vaddr < range.max)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+/// Build the `DynamicInfo` for `elf`, resolving each relocation's target
+/// virtual address against the memory ranges already discovered in
+/// `memranges` (relocations in `.rela.dyn`/`.rela.plt` are patched by
+/// `ld.so` at runtime, so `r_offset` is a vaddr, not a file offset).
+pub(crate) fn analyze(elf: &Elf, memranges: &[(MRange, MetaData)]) -> DynamicInfo {
+    let dynstrtab = &elf.dynstrtab;
+
+    let mut relocations = Vec::new();
+    for reloc in elf
+        .dynrelas
+        .iter()
+        .chain(elf.dynrels.iter())
+        .chain(elf.pltrelocs.iter())
+    {
+        let symbol = elf
+            .dynsyms
+            .get(reloc.r_sym)
+            .and_then(|sym| dynstrtab.get_unsafe(sym.st_name))
+            .filter(|name| !name.is_empty())
+            .map(String::from);
+        relocations.push(Relocation {
+            offset: reloc.r_offset,
+            symbol,
+            target_tag: containing_tag(memranges, reloc.r_offset),
+        });
+    }
+
+    let mut symbols = Vec::new();
+    for sym in elf.dynsyms.iter() {
+        if sym.st_value == 0 || sym.st_shndx == 0 {
+            continue;
+        }
+        if let Some(name) = dynstrtab.get_unsafe(sym.st_name) {
+            if !name.is_empty() {
+                let size = sym.st_size.max(1);
+                symbols.push(((sym.st_value, sym.st_value + size).into(), name.to_string()));
+            }
+        }
+    }
+
+    let needed = elf.libraries.iter().map(|lib| lib.to_string()).collect();
+    let (init, fini) = elf
+        .dynamic
+        .as_ref()
+        .map(|dynamic| {
+            (
+                Some(dynamic.info.init as u64),
+                Some(dynamic.info.fini as u64),
+            )
+        })
+        .unwrap_or((None, None));
+
+    DynamicInfo {
+        relocations,
+        needed,
+        soname: elf.soname.map(String::from),
+        init,
+        fini,
+        entry: elf.entry,
+        symbols,
+    }
+}
+
+fn containing_tag(ranges: &[(MRange, MetaData)], offset: u64) -> Option<Tag> {
+    ranges
+        .iter()
+        .find(|(range, _)| range.min <= offset && offset < range.max)
+        .map(|(_, meta)| meta.tag.clone())
+}
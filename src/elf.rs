@@ -0,0 +1,44 @@
+use log::*;
+
+use crate::{MRange, MetaData};
+
+/// Populate `franges`/`memranges` from an ELF's program and section
+/// headers. Each program header and each allocated (`SHF_ALLOC`) section is
+/// assigned the next id from `next_id`, shared by its franges and memranges
+/// entries, so they can be paired back up later. Sections that are not
+/// allocated are not mapped at runtime and get no id.
+pub(crate) fn analyze(
+    elf: &goblin::elf::Elf,
+    franges: &mut Vec<(MRange, MetaData)>,
+    memranges: &mut Vec<(MRange, MetaData)>,
+    next_id: &mut u32,
+) {
+    for phdr in &elf.program_headers {
+        let range = phdr.file_range();
+        let vmrange = phdr.vm_range();
+        let mut tag: MetaData = phdr.into();
+        tag.segment_id = Some(*next_id);
+        *next_id += 1;
+        debug!("{:?}", range);
+        franges.push(((range.start as u64, range.end as u64).into(), tag.clone()));
+        memranges.push(((vmrange.start as u64, vmrange.end as u64).into(), tag));
+    }
+    for shdr in &elf.section_headers {
+        if shdr.sh_size == 0 {
+            continue;
+        }
+        let vmrange = shdr.vm_range();
+        let mut tag = MetaData::from(shdr);
+        // fixme
+        tag.name = elf.shdr_strtab.get_unsafe(shdr.sh_name).map(String::from);
+        if shdr.is_alloc() {
+            tag.segment_id = Some(*next_id);
+            *next_id += 1;
+        }
+        if let Some(range) = shdr.file_range() {
+            debug!("{:?}", range);
+            franges.push(((range.start as u64, range.end as u64).into(), tag.clone()));
+        }
+        memranges.push(((vmrange.start as u64, vmrange.end as u64).into(), tag).into());
+    }
+}
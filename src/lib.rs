@@ -1,9 +1,16 @@
-use log::*;
-
 // we are extending the goblin api, so we export goblins types so
 // others will use it directly instead of depending on goblin + metagoblin
 pub use goblin::*;
 
+mod archive;
+mod content;
+mod dynamic;
+mod elf;
+mod mach;
+mod pe;
+
+pub use dynamic::{DynamicInfo, Relocation};
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 /// A range of memory
 pub struct MRange {
@@ -33,7 +40,7 @@ impl From<(u64, u64)> for MRange {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Symbolically tags an address range in a binary
 pub enum Tag {
     Meta,
@@ -45,6 +52,9 @@ pub enum Tag {
     SymbolTable,
     Zero,
     ASCII,
+    /// A block of high-entropy bytes, likely packed, compressed, or
+    /// encrypted.
+    HighEntropy,
     Unknown,
 }
 
@@ -53,6 +63,12 @@ pub struct MetaData {
     pub tag: Tag,
     pub name: Option<String>,
     pub memory: Option<Segment>,
+    /// Identifies the header (program header, section, PE section table
+    /// entry, Mach-O segment, ...) this entry was derived from. A franges
+    /// entry and a memranges entry sharing the same id came from the same
+    /// header, letting `Analysis::vaddr_to_offset`/`offset_to_vaddr` pair
+    /// them up.
+    pub segment_id: Option<u32>,
 }
 
 impl MetaData {
@@ -63,6 +79,17 @@ impl MetaData {
             None
         }
     }
+
+    /// A `Tag::Unknown` entry with no name or backing segment, used to fill
+    /// gaps between known ranges.
+    fn unknown() -> Self {
+        MetaData {
+            tag: Tag::Unknown,
+            name: None,
+            memory: None,
+            segment_id: None,
+        }
+    }
 }
 
 impl<'a> From<&'a goblin::elf::ProgramHeader> for MetaData {
@@ -78,13 +105,20 @@ impl<'a> From<&'a goblin::elf::ProgramHeader> for MetaData {
             PT_DYNAMIC => Tag::Meta,
             PT_LOAD => {
                 let permissions = Permissions::from(phdr);
-                let segment = Segment::new(permissions);
+                let segment = Segment::new(permissions)
+                    .with_file_offset(phdr.p_offset)
+                    .with_file_size(phdr.p_filesz);
                 memory = Some(segment);
                 Tag::Code
             }
             _ => Tag::Unknown,
         };
-        MetaData { name, tag, memory }
+        MetaData {
+            name,
+            tag,
+            memory,
+            segment_id: None,
+        }
     }
 }
 
@@ -107,13 +141,20 @@ impl<'a> From<&'a goblin::elf::SectionHeader> for MetaData {
             }
             SHT_PROGBITS | SHT_FINI_ARRAY | SHT_INIT_ARRAY => {
                 let permissions = Permissions::from(shdr);
-                let segment = Segment::new(permissions);
+                let segment = Segment::new(permissions)
+                    .with_file_offset(shdr.sh_offset)
+                    .with_file_size(shdr.sh_size);
                 memory = Some(segment);
                 Tag::Code
             }
             _ => Tag::Unknown,
         };
-        MetaData { name, tag, memory }
+        MetaData {
+            name,
+            tag,
+            memory,
+            segment_id: None,
+        }
     }
 }
 
@@ -170,6 +211,17 @@ impl<'a> From<&'a goblin::elf::SectionHeader> for Permissions {
 pub struct Segment {
     pub permissions: Permissions,
     pub alignment: Option<usize>,
+    /// The file offset backing the start of this segment's memory range,
+    /// if any (`None` for sections with no file backing, e.g. ELF NOBITS or
+    /// Mach-O zero-fill sections; other entirely zero-filled segments may
+    /// instead carry a `file_size` of 0).
+    pub file_offset: Option<u64>,
+    /// How many bytes from `file_offset` actually back this segment. May be
+    /// smaller than the segment's memory range (e.g. a `PT_LOAD` with
+    /// trailing bss, or a packed PE section whose `VirtualSize` exceeds its
+    /// `SizeOfRawData`); the remainder has no file backing and should be
+    /// zero-filled.
+    pub file_size: Option<u64>,
 }
 
 impl Segment {
@@ -177,47 +229,318 @@ impl Segment {
         Segment {
             permissions,
             alignment: None,
+            file_offset: None,
+            file_size: None,
         }
     }
+
+    pub fn with_file_offset(mut self, file_offset: u64) -> Self {
+        self.file_offset = Some(file_offset);
+        self
+    }
+
+    pub fn with_file_size(mut self, file_size: u64) -> Self {
+        self.file_size = Some(file_size);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A contiguous, executable blob of bytes and the virtual address it loads
+/// at, ready for a disassembler or gadget scanner.
+pub struct CodeSegment {
+    pub addr: u64,
+    pub bytes: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct Analysis {
     pub franges: Vec<(MRange, MetaData)>,
     pub memranges: Vec<(MRange, MetaData)>,
+    /// Dynamic-linking info (relocations, needed libraries, symbols), when
+    /// `goblin` was an `Object::Elf`.
+    pub dynamic: Option<DynamicInfo>,
 }
 
 impl Analysis {
-    pub fn new<'a>(goblin: &Object<'a>) -> Self {
+    /// Analyze `goblin`, tagging and ranging every structure it knows how to
+    /// parse. `bytes` is the buffer `goblin` was parsed from, needed to
+    /// locate members within an `Object::Archive`.
+    pub fn new<'a>(goblin: &Object<'a>, bytes: &'a [u8]) -> Self {
         let mut franges = Vec::default();
         let mut memranges = Vec::default();
+        let mut dynamic = None;
+        let mut next_id = 0u32;
         match goblin {
             &Object::Elf(ref elf) => {
-                for phdr in &elf.program_headers {
-                    let range = phdr.file_range();
-                    let vmrange = phdr.vm_range();
-                    let tag: MetaData = phdr.into();
-                    debug!("{:?}", range);
-                    franges.push(((range.start as u64, range.end as u64).into(), tag.clone()));
-                    memranges.push(((vmrange.start as u64, vmrange.end as u64).into(), tag));
+                elf::analyze(elf, &mut franges, &mut memranges, &mut next_id);
+                dynamic = Some(dynamic::analyze(elf, &memranges));
+            }
+            &Object::PE(ref pe) => pe::analyze(pe, &mut franges, &mut memranges, &mut next_id),
+            &Object::Mach(ref mach) => {
+                mach::analyze(mach, &mut franges, &mut memranges, &mut next_id)
+            }
+            &Object::Archive(ref archive) => archive::analyze(archive, bytes, &mut franges),
+            _ => (),
+        }
+        Analysis {
+            franges,
+            memranges,
+            dynamic,
+        }
+    }
+
+    /// Sort `franges` by their start, then synthesize `Tag::Unknown` filler
+    /// entries for every byte of `0..file_len` not already covered by an
+    /// existing range, so the union of `franges` exactly tiles the file.
+    /// Overlapping ranges (e.g. a `PT_LOAD` segment and the sections inside
+    /// it) are both kept.
+    pub fn normalize(&mut self, file_len: u64) {
+        self.franges.sort_by_key(|&(range, _)| range.min);
+
+        let mut tiled = Vec::with_capacity(self.franges.len());
+        let mut cursor = 0u64;
+        for (range, meta) in self.franges.drain(..) {
+            if range.min > cursor {
+                tiled.push(((cursor, range.min).into(), MetaData::unknown()));
+            }
+            cursor = cursor.max(range.max);
+            tiled.push((range, meta));
+        }
+        if cursor < file_len {
+            tiled.push(((cursor, file_len).into(), MetaData::unknown()));
+        }
+        self.franges = tiled;
+    }
+
+    /// Return every entry in `franges` whose range contains `offset`, e.g. a
+    /// `PT_LOAD` segment and the sections nested inside it. Requires
+    /// `franges` to already be sorted by `min` (see `normalize`).
+    pub fn containing(&self, offset: u64) -> Vec<&(MRange, MetaData)> {
+        let idx = self
+            .franges
+            .partition_point(|(range, _)| range.min <= offset);
+        self.franges[..idx]
+            .iter()
+            .filter(|(range, _)| offset < range.max)
+            .collect()
+    }
+
+    /// Inspect the bytes backing each file range and split it into ASCII
+    /// string, zero-fill, and high-entropy subranges, falling back to the
+    /// range's existing tag for whatever is left over. Each subrange keeps
+    /// its parent's `memory` and `name`.
+    ///
+    /// Subranges are appended in `franges`'s incoming (post-`normalize`)
+    /// order, which isn't sorted by `min` once overlapping ranges (e.g. a
+    /// `PT_LOAD` segment and a section nested inside it) are each split in
+    /// turn, so `franges` is re-sorted by `min` before returning to preserve
+    /// `containing`'s precondition.
+    pub fn refine(&mut self, bytes: &[u8]) {
+        let mut refined = Vec::with_capacity(self.franges.len());
+        for (range, meta) in self.franges.drain(..) {
+            let start = range.min as usize;
+            let end = (range.max as usize).min(bytes.len());
+            if start >= end {
+                refined.push((range, meta));
+                continue;
+            }
+            refined.extend(content::split(&bytes[start..end], range.min, &meta));
+        }
+        refined.sort_by_key(|&(range, _)| range.min);
+        self.franges = refined;
+    }
+
+    /// Build a `CodeSegment` for every memrange whose permissions include
+    /// execute, copying its file-backed bytes (clipped to `Segment::file_size`,
+    /// e.g. a `PT_LOAD` with trailing bss or a packed PE section whose
+    /// `VirtualSize` exceeds its `SizeOfRawData`) and zero-padding the rest
+    /// out to the full memory length.
+    pub fn executable_segments(&self, bytes: &[u8]) -> Vec<CodeSegment> {
+        self.memranges
+            .iter()
+            .filter_map(|(range, meta)| {
+                let segment = meta.memory.as_ref()?;
+                if !segment.permissions.execute() {
+                    return None;
                 }
-                for shdr in &elf.section_headers {
-                    if shdr.sh_size == 0 {
-                        continue;
+                let memsize = range.len() as usize;
+                let mut data = vec![0u8; memsize];
+                if let Some(offset) = segment.file_offset {
+                    let start = offset as usize;
+                    let backed = segment
+                        .file_size
+                        .map(|size| size as usize)
+                        .unwrap_or(memsize)
+                        .min(memsize);
+                    let end = (start + backed).min(bytes.len());
+                    if start < end {
+                        data[..end - start].copy_from_slice(&bytes[start..end]);
                     }
-                    let vmrange = shdr.vm_range();
-                    let mut tag = MetaData::from(shdr);
-                    // fixme
-                    tag.name = elf.shdr_strtab.get_unsafe(shdr.sh_name).map(String::from);
-                    if let Some(range) = shdr.file_range() {
-                        debug!("{:?}", range);
-                        franges.push(((range.start as u64, range.end as u64).into(), tag.clone()));
-                    }
-                    memranges.push(((vmrange.start as u64, vmrange.end as u64).into(), tag).into());
                 }
-            }
-            _ => (),
+                Some(CodeSegment {
+                    addr: range.min,
+                    bytes: data,
+                })
+            })
+            .collect()
+    }
+
+    /// Convert a virtual address to its backing file offset, by finding the
+    /// memrange covering `vaddr` and interpolating into the franges entry
+    /// that shares its segment id. Returns `None` if `vaddr` isn't mapped,
+    /// or falls in a NOBITS/zero-fill region with no file backing.
+    pub fn vaddr_to_offset(&self, vaddr: u64) -> Option<u64> {
+        let (mem_range, meta) = self
+            .memranges
+            .iter()
+            .find(|(range, _)| range.min <= vaddr && vaddr < range.max)?;
+        let id = meta.segment_id?;
+        let segment_min = self.segment_file_min(id)?;
+        let offset = segment_min + (vaddr - mem_range.min);
+        if self.offset_in_segment(id, offset) {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
+    /// Convert a file offset to its virtual address, by finding the franges
+    /// entry covering `offset` and interpolating into the memrange that
+    /// shares its segment id. Returns `None` if `offset` isn't part of any
+    /// loadable segment.
+    pub fn offset_to_vaddr(&self, offset: u64) -> Option<u64> {
+        let id = self
+            .franges
+            .iter()
+            .find(|(range, meta)| {
+                meta.segment_id.is_some() && range.min <= offset && offset < range.max
+            })
+            .and_then(|(_, meta)| meta.segment_id)?;
+        let segment_min = self.segment_file_min(id)?;
+        let (mem_range, _) = self
+            .memranges
+            .iter()
+            .find(|(_, meta)| meta.segment_id == Some(id))?;
+        let vaddr = mem_range.min + (offset - segment_min);
+        if vaddr < mem_range.max {
+            Some(vaddr)
+        } else {
+            None
         }
-        Analysis { franges, memranges }
+    }
+
+    /// The smallest `min` among all franges entries sharing `id`, i.e. the
+    /// true start of the segment even if `refine` has since split it into
+    /// several subranges.
+    fn segment_file_min(&self, id: u32) -> Option<u64> {
+        self.franges
+            .iter()
+            .filter(|(_, meta)| meta.segment_id == Some(id))
+            .map(|(range, _)| range.min)
+            .min()
+    }
+
+    fn offset_in_segment(&self, id: u32, offset: u64) -> bool {
+        self.franges.iter().any(|(range, meta)| {
+            meta.segment_id == Some(id) && range.min <= offset && offset < range.max
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(tag: Tag) -> MetaData {
+        MetaData {
+            tag,
+            name: None,
+            memory: None,
+            segment_id: None,
+        }
+    }
+
+    fn analysis_of(franges: Vec<(MRange, MetaData)>) -> Analysis {
+        Analysis {
+            franges,
+            memranges: Vec::new(),
+            dynamic: None,
+        }
+    }
+
+    #[test]
+    fn normalize_fills_gaps_and_keeps_overlapping_ranges() {
+        let mut analysis = analysis_of(vec![
+            ((0, 10).into(), meta(Tag::Code)),
+            ((3, 6).into(), meta(Tag::ASCII)),
+        ]);
+        analysis.normalize(12);
+
+        let ranges: Vec<MRange> = analysis.franges.iter().map(|(r, _)| *r).collect();
+        assert_eq!(ranges, vec![(0, 10).into(), (3, 6).into(), (10, 12).into()]);
+        assert_eq!(analysis.franges[2].1.tag, Tag::Unknown);
+    }
+
+    #[test]
+    fn containing_finds_every_overlapping_range() {
+        let mut analysis = analysis_of(vec![
+            ((0, 10).into(), meta(Tag::Code)),
+            ((3, 6).into(), meta(Tag::ASCII)),
+        ]);
+        analysis.normalize(10);
+
+        let hits = analysis.containing(4);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().any(|(_, m)| m.tag == Tag::Code));
+        assert!(hits.iter().any(|(_, m)| m.tag == Tag::ASCII));
+
+        assert_eq!(analysis.containing(8).len(), 1);
+        assert!(analysis.containing(11).is_empty());
+    }
+
+    #[test]
+    fn refine_keeps_franges_sorted_after_splitting_overlapping_ranges() {
+        // bytes[0..5] are non-ASCII filler; bytes[5..10] are a NUL-terminated
+        // ASCII run, so refining the outer [0,10) range alone splits it into
+        // two pieces, (0,5) and (5,10) — the second piece's min (5) is
+        // larger than the nested [3,6) range's min (3). refine() must still
+        // leave franges sorted by min once the nested range's own (smaller)
+        // pieces are appended after the outer range's.
+        let bytes = [0xAB, 0xAB, 0xAB, 0xAB, 0xAB, b'A', b'A', b'A', b'A', 0u8];
+        let mut analysis = analysis_of(vec![
+            ((0, 10).into(), meta(Tag::Code)),
+            ((3, 6).into(), meta(Tag::Data)),
+        ]);
+        analysis.refine(&bytes);
+
+        let mins: Vec<u64> = analysis.franges.iter().map(|(r, _)| r.min).collect();
+        let mut sorted = mins.clone();
+        sorted.sort();
+        assert_eq!(mins, sorted, "refine must leave franges sorted by min");
+    }
+
+    #[test]
+    fn vaddr_and_offset_round_trip_through_shared_segment_id() {
+        let franges_meta = MetaData {
+            segment_id: Some(7),
+            ..meta(Tag::Code)
+        };
+        let memranges_meta = MetaData {
+            memory: Some(Segment::new(Permissions::new(true, false, true))),
+            segment_id: Some(7),
+            ..meta(Tag::Code)
+        };
+        let analysis = Analysis {
+            franges: vec![((100, 200).into(), franges_meta)],
+            memranges: vec![((0x2000, 0x2064).into(), memranges_meta)],
+            dynamic: None,
+        };
+
+        assert_eq!(analysis.vaddr_to_offset(0x2005), Some(105));
+        assert_eq!(analysis.offset_to_vaddr(105), Some(0x2005));
+
+        assert_eq!(analysis.vaddr_to_offset(0x3000), None);
+        assert_eq!(analysis.offset_to_vaddr(300), None);
     }
 }
@@ -0,0 +1,127 @@
+use goblin::mach::{Mach, MachO, MultiArch, SingleArch};
+
+use crate::{MRange, MetaData, Permissions, Segment, Tag};
+
+// VM_PROT_* flags, as defined in mach/vm_prot.h
+const VM_PROT_READ: u32 = 0x1;
+const VM_PROT_WRITE: u32 = 0x2;
+const VM_PROT_EXECUTE: u32 = 0x4;
+
+/// Populate `franges`/`memranges` from a Mach-O's load commands. Each
+/// segment and section is assigned the next id from `next_id`, shared by
+/// its franges and memranges entries, so they can be paired back up later.
+///
+/// Fat (universal) binaries are not expanded into all their constituent
+/// architectures; only the first slice that parses as a thin Mach-O is
+/// analyzed, since franges/memranges have no notion of per-architecture
+/// overlap.
+pub(crate) fn analyze(
+    mach: &Mach,
+    franges: &mut Vec<(MRange, MetaData)>,
+    memranges: &mut Vec<(MRange, MetaData)>,
+    next_id: &mut u32,
+) {
+    match mach {
+        Mach::Binary(macho) => analyze_macho(macho, franges, memranges, next_id),
+        Mach::Fat(multi) => {
+            if let Some(macho) = first_macho(multi) {
+                analyze_macho(&macho, franges, memranges, next_id);
+            }
+        }
+    }
+}
+
+/// The first architecture slice of a fat binary that parses as a thin
+/// Mach-O, skipping any slice that is itself an archive.
+fn first_macho<'a>(multi: &MultiArch<'a>) -> Option<MachO<'a>> {
+    let narches = multi.arches().map(|arches| arches.len()).unwrap_or(0);
+    (0..narches).find_map(|i| match multi.get(i) {
+        Ok(SingleArch::MachO(macho)) => Some(macho),
+        _ => None,
+    })
+}
+
+fn analyze_macho(
+    macho: &MachO,
+    franges: &mut Vec<(MRange, MetaData)>,
+    memranges: &mut Vec<(MRange, MetaData)>,
+    next_id: &mut u32,
+) {
+    for segment in &macho.segments {
+        let name = segment.name().unwrap_or("").to_string();
+        let permissions = permissions_from_prot(segment.initprot as u32);
+        let meta = MetaData {
+            tag: if permissions.execute() {
+                Tag::Code
+            } else {
+                Tag::Data
+            },
+            name: Some(name),
+            memory: Some(
+                Segment::new(permissions.clone())
+                    .with_file_offset(segment.fileoff)
+                    .with_file_size(segment.filesize),
+            ),
+            segment_id: Some(*next_id),
+        };
+        *next_id += 1;
+
+        if segment.filesize > 0 {
+            let foff = segment.fileoff;
+            franges.push(((foff, foff + segment.filesize).into(), meta.clone()));
+        }
+        if segment.vmsize > 0 {
+            let vmin = segment.vmaddr;
+            memranges.push(((vmin, vmin + segment.vmsize).into(), meta));
+        }
+
+        if let Ok(sections) = segment.sections() {
+            for (section, _) in sections {
+                let sname = section.name().unwrap_or("").to_string();
+                // __cstring always lives in __TEXT, which is mapped R-X, so
+                // it must be checked before the segment-exec check rather
+                // than after, or it's always shadowed by `Tag::Code`.
+                let stag = if sname == "__cstring" {
+                    Tag::ASCII
+                } else if permissions.execute() {
+                    Tag::Code
+                } else {
+                    Tag::Data
+                };
+                // Zero-fill sections (e.g. __bss, __common) have no file
+                // backing and record a file offset of 0.
+                let file_backed = section.offset != 0;
+                let mut smemory = Segment::new(permissions.clone());
+                if file_backed {
+                    smemory = smemory
+                        .with_file_offset(section.offset as u64)
+                        .with_file_size(section.size);
+                }
+                let smeta = MetaData {
+                    tag: stag,
+                    name: Some(sname),
+                    memory: Some(smemory),
+                    segment_id: Some(*next_id),
+                };
+                *next_id += 1;
+
+                let size = section.size;
+                if size > 0 {
+                    if file_backed {
+                        let foff = section.offset as u64;
+                        franges.push(((foff, foff + size).into(), smeta.clone()));
+                    }
+                    memranges.push(((section.addr, section.addr + size).into(), smeta));
+                }
+            }
+        }
+    }
+}
+
+fn permissions_from_prot(prot: u32) -> Permissions {
+    Permissions::new(
+        prot & VM_PROT_READ != 0,
+        prot & VM_PROT_WRITE != 0,
+        prot & VM_PROT_EXECUTE != 0,
+    )
+}
@@ -15,7 +15,9 @@ fn run () -> error::Result<()> {
             let mut fd = File::open(path)?;
             let buffer = { let mut v = Vec::new(); fd.read_to_end(&mut v).unwrap(); v};
             let res = goblin::Object::parse(&buffer)?;
-            let analysis = metagoblin::Analysis::new(&res);
+            let mut analysis = metagoblin::Analysis::new(&res, &buffer);
+            analysis.normalize(buffer.len() as u64);
+            analysis.refine(&buffer);
             //println!("{:#?}", analysis);
             for (range, data) in analysis.franges.iter() {
                 print!("{:#x}..{:#x}({}) -> ", range.min, range.max, range.len() - 1);
@@ -0,0 +1,58 @@
+use goblin::pe::section_table::{IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ, IMAGE_SCN_MEM_WRITE};
+use goblin::pe::PE;
+
+use crate::{MRange, MetaData, Permissions, Segment, Tag};
+
+/// Populate `franges`/`memranges` from a PE's section table. Each section
+/// is assigned the next id from `next_id`, shared by its franges and
+/// memranges entries, so they can be paired back up later.
+pub(crate) fn analyze(
+    pe: &PE,
+    franges: &mut Vec<(MRange, MetaData)>,
+    memranges: &mut Vec<(MRange, MetaData)>,
+    next_id: &mut u32,
+) {
+    let image_base = pe.image_base as u64;
+    for section in &pe.sections {
+        let name = section.name().unwrap_or("").to_string();
+        let characteristics = section.characteristics;
+        let permissions = Permissions::new(
+            characteristics & IMAGE_SCN_MEM_READ != 0,
+            characteristics & IMAGE_SCN_MEM_WRITE != 0,
+            characteristics & IMAGE_SCN_MEM_EXECUTE != 0,
+        );
+        let tag = if permissions.execute() {
+            Tag::Code
+        } else {
+            match name.as_str() {
+                ".idata" | ".edata" => Tag::Meta,
+                ".rsrc" => Tag::Data,
+                _ => Tag::Unknown,
+            }
+        };
+        let foff = section.pointer_to_raw_data as u64;
+        let fsize = section.size_of_raw_data as u64;
+        let memory = Some(
+            Segment::new(permissions)
+                .with_file_offset(foff)
+                .with_file_size(fsize),
+        );
+        let meta = MetaData {
+            tag,
+            name: Some(name),
+            memory,
+            segment_id: Some(*next_id),
+        };
+        *next_id += 1;
+
+        if fsize > 0 {
+            franges.push(((foff, foff + fsize).into(), meta.clone()));
+        }
+
+        let vmin = image_base + section.virtual_address as u64;
+        let vsize = section.virtual_size as u64;
+        if vsize > 0 {
+            memranges.push(((vmin, vmin + vsize).into(), meta));
+        }
+    }
+}